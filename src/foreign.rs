@@ -0,0 +1,79 @@
+//! Transferring ownership of arbitrary Rust values across the FFI boundary.
+
+use core::ffi::c_void;
+use std::sync::Arc;
+
+/// Types that can be converted to and from a raw `*mut c_void` pointer while
+/// transferring ownership, for use as the `data` payload of a native
+/// callback.
+///
+/// This mirrors the `into_foreign`/`from_foreign`/`borrow` split used by
+/// similar FFI crates: `into_foreign` hands ownership to the native side,
+/// `from_foreign` is the exact inverse and must only be called once per
+/// `into_foreign` call, and `borrow` allows inspecting the value without
+/// taking ownership back (e.g. from a callback that may fire more than
+/// once).
+pub trait ForeignOwnable {
+    /// The type yielded by [`Self::borrow`].
+    type Borrowed<'a>;
+
+    /// Converts `self` into a raw pointer, transferring ownership to the
+    /// caller of this method.
+    fn into_foreign(self) -> *mut c_void;
+
+    /// Reconstructs `Self` from a pointer previously returned by
+    /// [`Self::into_foreign`].
+    ///
+    /// # Safety
+    ///
+    /// `p` must have been obtained from a call to [`Self::into_foreign`],
+    /// and this function must not be called more than once for the same
+    /// pointer.
+    unsafe fn from_foreign(p: *mut c_void) -> Self;
+
+    /// Borrows the value pointed to by `p` without taking ownership.
+    ///
+    /// # Safety
+    ///
+    /// `p` must have been obtained from a call to [`Self::into_foreign`],
+    /// the value must not have been reclaimed yet via [`Self::from_foreign`],
+    /// and it must outlive the returned borrow.
+    unsafe fn borrow<'a>(p: *mut c_void) -> Self::Borrowed<'a>;
+}
+
+impl<T> ForeignOwnable for Box<T> {
+    type Borrowed<'a> = &'a T;
+
+    fn into_foreign(self) -> *mut c_void {
+        Box::into_raw(self).cast::<c_void>()
+    }
+
+    unsafe fn from_foreign(p: *mut c_void) -> Self {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { Box::from_raw(p.cast::<T>()) }
+    }
+
+    unsafe fn borrow<'a>(p: *mut c_void) -> Self::Borrowed<'a> {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { &*p.cast::<T>() }
+    }
+}
+
+impl<T> ForeignOwnable for Arc<T> {
+    type Borrowed<'a> = &'a T;
+
+    fn into_foreign(self) -> *mut c_void {
+        // Leaks one strong reference, which `from_foreign` reclaims.
+        Arc::into_raw(self).cast_mut().cast::<c_void>()
+    }
+
+    unsafe fn from_foreign(p: *mut c_void) -> Self {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { Arc::from_raw(p.cast::<T>()) }
+    }
+
+    unsafe fn borrow<'a>(p: *mut c_void) -> Self::Borrowed<'a> {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { &*p.cast::<T>() }
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! This library can be used to receive callbacks on vsync signals
 
-use core::ffi::{c_int, c_void};
+use core::ffi::{c_int, c_longlong, c_void};
 
 use log::debug;
 use ohos_sys::vsync::{
@@ -10,7 +10,14 @@ use ohos_sys::vsync::{
     OH_NativeVSync_GetPeriod, OH_NativeVSync_RequestFrame,
 };
 
+mod foreign;
 mod log;
+mod scope_guard;
+mod stream;
+
+pub use foreign::ForeignOwnable;
+pub use scope_guard::ScopeGuard;
+pub use stream::VsyncStream;
 
 pub struct NativeVsync {
     raw: *mut OH_NativeVSync,
@@ -82,17 +89,126 @@ impl NativeVsync {
         self,
         callback: OH_NativeVSync_FrameCallback,
     ) -> Result<(), NativeVsyncError> {
-        let res =
-            unsafe { OH_NativeVSync_RequestFrame(self.raw, callback, self.raw as *mut c_void) };
+        // Guards `self` so that it is destroyed (via its `Drop` impl) if the
+        // request below fails, instead of relying on an implicit drop at the
+        // end of the function.
+        let guard = ScopeGuard::new(self, |_self| {});
+        let raw = guard.get().raw;
+        let res = unsafe { OH_NativeVSync_RequestFrame(raw, callback, raw as *mut c_void) };
         if res == 0 {
-            core::mem::forget(self);
+            core::mem::forget(guard.dismiss());
             Ok(())
         } else {
-            // implicit drop / destroy
             Err(NativeVsyncError::RawErr(res))
         }
     }
 
+    /// Request a callback on the next Vsync frame, invoking a safe closure.
+    ///
+    /// `f` is called exactly once, on the vsync thread, with the frame
+    /// timestamp (in nanoseconds) once the next frame is ready. This is a
+    /// safe wrapper around [`Self::request_raw_callback`]: the closure is
+    /// boxed and handed to the native side as the `data` pointer, and an
+    /// internal trampoline reconstructs and invokes it, so callers never
+    /// have to deal with the raw `*mut c_void` or write their own
+    /// `extern "C"` function.
+    pub fn request_frame<F: FnOnce(i64) + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<(), NativeVsyncError> {
+        let data = Box::into_raw(Box::new(f)).cast::<c_void>();
+        // SAFETY: `data` was just created via `Box::into_raw` above, so it is
+        // a valid pointer to a boxed `F`, and `frame_callback_trampoline::<F>`
+        // reconstructs it with the same type. `F: Send` satisfies the
+        // requirement that `data` be safe to use from the vsync thread.
+        let res = unsafe { self.request_raw_callback(Some(frame_callback_trampoline::<F>), data) };
+        if res.is_err() {
+            // The request failed, so the trampoline will never run: reclaim
+            // and drop the closure here instead of leaking it.
+            drop(unsafe { Box::from_raw(data.cast::<F>()) });
+        }
+        res
+    }
+
+    /// Request a callback on the next Vsync frame, handing it ownership of
+    /// `data` via [`ForeignOwnable`].
+    ///
+    /// `cb` is called exactly once, on the vsync thread, with the frame
+    /// timestamp (in nanoseconds) and `data` reconstructed via
+    /// `D::from_foreign`. This subsumes the raw `self`-as-payload pattern of
+    /// [`Self::request_raw_callback_with_self`]: any `ForeignOwnable` type
+    /// (e.g. a `Box<T>` or an `Arc<T>`) can be threaded through the native
+    /// callback without the caller ever touching a raw pointer. `D: Send`
+    /// is required because `cb` runs on the vsync thread.
+    pub fn request_frame_owned<D: ForeignOwnable + Send + 'static>(
+        &self,
+        data: D,
+        cb: fn(i64, D),
+    ) -> Result<(), NativeVsyncError> {
+        let payload = Box::into_raw(Box::new((cb, data.into_foreign()))).cast::<c_void>();
+        // SAFETY: `payload` was just created from a `Box<(fn(i64, D), *mut
+        // c_void)>` above, and `owned_callback_trampoline::<D>`
+        // reconstructs it with the same type.
+        let res =
+            unsafe { self.request_raw_callback(Some(owned_callback_trampoline::<D>), payload) };
+        if res.is_err() {
+            // The request failed, so the trampoline never ran; reclaim the
+            // payload (and the foreign `data`) here instead of leaking it.
+            let (_, raw_data) =
+                *unsafe { Box::from_raw(payload.cast::<(fn(i64, D), *mut c_void)>()) };
+            drop(unsafe { D::from_foreign(raw_data) });
+        }
+        res
+    }
+
+    /// Like [`Self::request_frame_owned`], but on failure hands `data` back
+    /// to the caller instead of silently dropping it, so retry logic is
+    /// possible.
+    pub fn try_request_frame_owned<D: ForeignOwnable + Send + 'static>(
+        &self,
+        data: D,
+        cb: fn(i64, D),
+    ) -> Result<(), (NativeVsyncError, D)> {
+        let payload = Box::new((cb, data.into_foreign()));
+        // Guards the payload so that, if this function exits before the
+        // request below returns (e.g. by unwinding), the payload and the
+        // foreign `data` are reclaimed and dropped instead of leaked. Both
+        // outcomes of the request dismiss the guard; the error path hands
+        // `data` back to the caller itself.
+        let guard = ScopeGuard::new(
+            Box::into_raw(payload),
+            |p: *mut (fn(i64, D), *mut c_void)| {
+                // SAFETY: The guard is still armed only if the request never
+                // returned, so the trampoline was never registered; this
+                // pointer was produced by `Box::into_raw` above and is
+                // reclaimed here exactly once.
+                let (_, raw_data) = *unsafe { Box::from_raw(p) };
+                drop(unsafe { D::from_foreign(raw_data) });
+            },
+        );
+        let data_ptr = guard.get().cast::<c_void>();
+        // SAFETY: `data_ptr` was just created from a `Box<(fn(i64, D), *mut
+        // c_void)>` above, and `owned_callback_trampoline::<D>`
+        // reconstructs it with the same type.
+        let res =
+            unsafe { self.request_raw_callback(Some(owned_callback_trampoline::<D>), data_ptr) };
+        match res {
+            Ok(()) => {
+                guard.dismiss();
+                Ok(())
+            }
+            Err(err) => {
+                // SAFETY: The request failed, so the trampoline never ran;
+                // reclaim the payload ourselves so we can hand the typed
+                // `data` back to the caller, rather than letting the guard's
+                // cleanup silently drop it.
+                let (_, raw_data) = *unsafe { Box::from_raw(guard.dismiss()) };
+                let data = unsafe { D::from_foreign(raw_data) };
+                Err((err, data))
+            }
+        }
+    }
+
     /// Returns the vsync period in nanoseconds.
     pub fn get_period(&self) -> Result<u64, NativeVsyncError> {
         let period = unsafe {
@@ -117,3 +233,40 @@ impl Drop for NativeVsync {
         unsafe { OH_NativeVSync_Destroy(self.raw) };
     }
 }
+
+/// Trampoline used by [`NativeVsync::request_frame`].
+///
+/// # Safety
+///
+/// `data` must be a pointer obtained from `Box::into_raw(Box::new(f))` for a
+/// closure `f: F`, and this function must be called at most once for that
+/// pointer (guaranteed by `OH_NativeVSync_RequestFrame` being single-shot).
+extern "C" fn frame_callback_trampoline<F: FnOnce(i64) + Send + 'static>(
+    timestamp: c_longlong,
+    data: *mut c_void,
+) {
+    // SAFETY: See function-level safety comment; `data` was created from a
+    // `Box<F>` by `request_frame` and handed to us untouched.
+    let f = unsafe { Box::from_raw(data.cast::<F>()) };
+    f(timestamp as i64);
+}
+
+/// Trampoline used by [`NativeVsync::request_frame_owned`].
+///
+/// # Safety
+///
+/// `data` must be a pointer obtained from
+/// `Box::into_raw(Box::new((cb, data.into_foreign())))` for the same `D`,
+/// and this function must be called at most once for that pointer
+/// (guaranteed by `OH_NativeVSync_RequestFrame` being single-shot).
+extern "C" fn owned_callback_trampoline<D: ForeignOwnable>(
+    timestamp: c_longlong,
+    data: *mut c_void,
+) {
+    // SAFETY: See function-level safety comment.
+    let (cb, raw_data) = *unsafe { Box::from_raw(data.cast::<(fn(i64, D), *mut c_void)>()) };
+    // SAFETY: `raw_data` was produced by `D::into_foreign` in
+    // `request_frame_owned` and is reclaimed here exactly once.
+    let owned = unsafe { D::from_foreign(raw_data) };
+    cb(timestamp as i64, owned);
+}
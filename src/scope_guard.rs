@@ -0,0 +1,40 @@
+//! A small RAII helper for cleaning up values on early-return/error paths.
+
+/// Holds a value together with an `FnOnce` cleanup that runs on drop, unless
+/// the guard has been [`dismiss`](ScopeGuard::dismiss)ed.
+///
+/// This is useful for functions that temporarily hand ownership of a
+/// pointer to native code: wrap the pointer (or the value it was derived
+/// from) in a `ScopeGuard` before making the FFI call, `dismiss()` it on
+/// success (the native side now owns it), and let it fall out of scope on
+/// failure so the cleanup runs automatically.
+pub struct ScopeGuard<T, F: FnOnce(T)> {
+    inner: Option<(T, F)>,
+}
+
+impl<T, F: FnOnce(T)> ScopeGuard<T, F> {
+    /// Creates a guard that runs `cleanup(value)` on drop, unless dismissed.
+    pub fn new(value: T, cleanup: F) -> Self {
+        Self {
+            inner: Some((value, cleanup)),
+        }
+    }
+
+    /// Disarms the guard's cleanup and returns the wrapped value.
+    pub fn dismiss(mut self) -> T {
+        self.inner.take().expect("ScopeGuard value missing").0
+    }
+
+    /// Borrows the wrapped value without disarming the guard.
+    pub fn get(&self) -> &T {
+        &self.inner.as_ref().expect("ScopeGuard value missing").0
+    }
+}
+
+impl<T, F: FnOnce(T)> Drop for ScopeGuard<T, F> {
+    fn drop(&mut self) {
+        if let Some((value, cleanup)) = self.inner.take() {
+            cleanup(value);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! An async [`Stream`] adapter over [`NativeVsync`].
+
+use core::ffi::{c_longlong, c_void};
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures_core::Stream;
+
+use crate::{NativeVsync, NativeVsyncError};
+
+struct Shared {
+    last_timestamp: Option<i64>,
+    waker: Option<Waker>,
+}
+
+struct Inner {
+    state: Mutex<Shared>,
+    /// Whether a clone of this `Inner`, leaked into a pending native
+    /// request via `Arc::into_raw`, has not yet been reclaimed.
+    ///
+    /// Both `stream_frame_trampoline` (on the vsync thread) and
+    /// `VsyncStream::drop` (on the owning thread) can race to reclaim the
+    /// same leaked clone once a request is outstanding. Both sides only
+    /// ever flip this from `true` to `false` via `swap`; whichever observes
+    /// `true` is the one that actually owns the leaked clone and must
+    /// `Arc::from_raw` + drop it. This makes the reclaim exactly-once
+    /// regardless of which side runs first.
+    leaked: AtomicBool,
+}
+
+/// A [`Stream`] of vsync timestamps, built on top of [`NativeVsync`].
+///
+/// Unlike [`NativeVsync::request_frame`], which delivers a single callback,
+/// `VsyncStream` automatically re-arms a new request after each delivered
+/// frame, so an async frame loop can simply `.await` it in a loop:
+///
+/// ```ignore
+/// let mut stream = VsyncStream::new(vsync)?;
+/// while let Some(timestamp) = stream.next().await {
+///     render(timestamp);
+/// }
+/// ```
+pub struct VsyncStream {
+    vsync: NativeVsync,
+    inner: Arc<Inner>,
+    /// Whether a `OH_NativeVSync_RequestFrame` call is currently pending,
+    /// i.e. whether a clone of `inner` has been leaked into the native
+    /// side via `Arc::into_raw` and not yet reclaimed.
+    outstanding: bool,
+    /// Set once re-arming the next request has failed, so the stream ends
+    /// after delivering the frame it already has in hand rather than
+    /// requesting again.
+    ended: bool,
+}
+
+impl VsyncStream {
+    /// Creates a new stream and immediately requests the first frame.
+    pub fn new(vsync: NativeVsync) -> Result<Self, NativeVsyncError> {
+        let inner = Arc::new(Inner {
+            state: Mutex::new(Shared {
+                last_timestamp: None,
+                waker: None,
+            }),
+            leaked: AtomicBool::new(false),
+        });
+        let mut stream = Self {
+            vsync,
+            inner,
+            outstanding: false,
+            ended: false,
+        };
+        stream.request_next_frame()?;
+        Ok(stream)
+    }
+
+    /// Arms a single `OH_NativeVSync_RequestFrame` call, leaking a clone of
+    /// `self.inner` as the callback `data`. Only one request may be
+    /// outstanding at a time.
+    fn request_next_frame(&mut self) -> Result<(), NativeVsyncError> {
+        debug_assert!(!self.outstanding, "a vsync request is already outstanding");
+        let data = Arc::into_raw(self.inner.clone())
+            .cast_mut()
+            .cast::<c_void>();
+        // Mark the clone as leaked before arming the request, since the
+        // trampoline may fire (and must be able to reclaim it) before
+        // `request_raw_callback` returns.
+        self.inner.leaked.store(true, Ordering::Release);
+        // SAFETY: `data` is a live `Arc<Inner>` clone leaked via
+        // `Arc::into_raw` above, and `stream_frame_trampoline` reclaims it
+        // with `Arc::from_raw` of the same type. `Inner` is `Send + Sync`,
+        // so it is safe to use from the vsync thread.
+        let res = unsafe {
+            self.vsync
+                .request_raw_callback(Some(stream_frame_trampoline), data)
+        };
+        match res {
+            Ok(()) => {
+                self.outstanding = true;
+                Ok(())
+            }
+            Err(err) => {
+                // The request failed, so the trampoline will never run for
+                // this clone: reclaim and drop it ourselves instead of
+                // leaking it. There is no race here, since a failed native
+                // call can never invoke the trampoline.
+                self.inner.leaked.store(false, Ordering::Release);
+                drop(unsafe { Arc::from_raw(data.cast::<Inner>()) });
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Stream for VsyncStream {
+    type Item = i64;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<i64>> {
+        let this = self.get_mut();
+        if this.ended {
+            return Poll::Ready(None);
+        }
+        let ready = {
+            let mut guard = this.inner.state.lock().unwrap();
+            match guard.last_timestamp.take() {
+                Some(timestamp) => Some(timestamp),
+                None => {
+                    guard.waker = Some(cx.waker().clone());
+                    None
+                }
+            }
+        };
+        match ready {
+            Some(timestamp) => {
+                this.outstanding = false;
+                // Deliver the frame we already have in hand even if the
+                // re-arm below fails; only end the stream on the next poll.
+                if this.request_next_frame().is_err() {
+                    this.ended = true;
+                }
+                Poll::Ready(Some(timestamp))
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for VsyncStream {
+    fn drop(&mut self) {
+        if self.outstanding {
+            // Race with `stream_frame_trampoline`, which may fire
+            // concurrently on the vsync thread between this check and the
+            // reclaim below. The atomic `swap` makes the reclaim
+            // exactly-once: whichever of the two observes `true` here is
+            // the one that must reclaim the leaked clone.
+            if self.inner.leaked.swap(false, Ordering::AcqRel) {
+                // SAFETY: We won the race above, so the leaked clone of
+                // `self.inner` created in `request_next_frame` is still
+                // live and not owned by anyone else. That request will now
+                // never fire, since `self.vsync` is about to be destroyed,
+                // so reclaim the leaked clone here to avoid leaking it
+                // permanently.
+                drop(unsafe { Arc::from_raw(Arc::as_ptr(&self.inner)) });
+            }
+        }
+    }
+}
+
+/// Trampoline used by [`VsyncStream::request_next_frame`].
+///
+/// # Safety
+///
+/// `data` must be a pointer obtained from `Arc::into_raw` on a clone of a
+/// `VsyncStream`'s `inner` field, and this function must be called at most
+/// once for that pointer (guaranteed by `OH_NativeVSync_RequestFrame` being
+/// single-shot).
+extern "C" fn stream_frame_trampoline(timestamp: c_longlong, data: *mut c_void) {
+    // SAFETY: `data` points to a live `Inner` for as long as the leaked
+    // `Arc<Inner>` clone it came from has not been reclaimed, which we only
+    // do below (after this borrow ends) or in `VsyncStream::drop` (which
+    // races with us via `leaked`, below). Borrowing it here does not touch
+    // the strong count, so it is safe even though we have not yet decided
+    // which side owns the reclaim.
+    let inner = unsafe { &*data.cast::<Inner>() };
+    let waker = {
+        let mut guard = inner.state.lock().unwrap();
+        guard.last_timestamp = Some(timestamp as i64);
+        guard.waker.take()
+    };
+    // Race with `VsyncStream::drop`: exactly one of it or this `swap`
+    // observes `true` and is responsible for reclaiming the leaked clone.
+    if inner.leaked.swap(false, Ordering::AcqRel) {
+        // SAFETY: We won the race above, so `data` is a leaked `Arc<Inner>`
+        // clone from `request_next_frame` that nobody else will reclaim.
+        drop(unsafe { Arc::from_raw(data.cast::<Inner>()) });
+    }
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}